@@ -0,0 +1,239 @@
+//! Prometheus metrics exporter for a Tesla Backup Gateway 2.
+//!
+//! This binary is only built when the `exporter` feature of `tega-bin` is
+//! enabled (`cargo run --features exporter --bin exporter`). It polls the
+//! gateway on a fixed interval and serves the most recent readings at
+//! `/metrics` in the Prometheus text exposition format.
+
+use clap::Parser;
+use rustls::Certificate;
+use std::{
+	fmt::Write,
+	fs::File,
+	io::{self, BufReader},
+	net::{IpAddr, SocketAddr},
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::Duration,
+};
+use tega_client::{Client, Credentials, Error};
+use tega_types::{
+	meters::{AggregateClass, MetersAggregates},
+	system_status::SystemStatus,
+};
+use tokio::{
+	net::{TcpListener, TcpStream},
+	sync::RwLock,
+};
+
+#[derive(Parser)]
+#[clap(
+	name = "exporter",
+	about = "A Prometheus metrics exporter for the Tesla Backup Gateway 2.",
+	version
+)]
+struct Arguments {
+	/// IP Address of the Tesla Backup Gateway 2.
+	ip_address: IpAddr,
+
+	/// Path to a PEM file containing the certificate for the Tesla Backup
+	/// Gateway 2.
+	certificate: PathBuf,
+
+	/// Username to log in with.
+	#[clap(long, env = "TEG_USERNAME", default_value = "customer")]
+	username: String,
+
+	/// Password to log in with.
+	#[clap(long, env = "TEG_PASSWORD", default_value = "")]
+	password: String,
+
+	/// Address to serve `/metrics` on.
+	#[clap(long, default_value = "0.0.0.0:9100")]
+	listen_address: SocketAddr,
+
+	/// How often to poll the gateway, in seconds.
+	#[clap(long, default_value = "10")]
+	interval_seconds: u64,
+}
+
+/// The most recently polled readings, shared between the polling task and
+/// the HTTP server.
+#[derive(Default)]
+struct Snapshot {
+	meters: Option<MetersAggregates>,
+	system_status: Option<SystemStatus>,
+	state_of_energy: Option<f64>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+	let arguments = Arguments::parse();
+	let certs = load_certificates_from_pem(&arguments.certificate)?;
+
+	let client = Client::new(
+		(arguments.ip_address, 443).try_into()?,
+		certs,
+		Credentials {
+			username: arguments.username,
+			password: arguments.password,
+		},
+	)?;
+
+	let snapshot = Arc::new(RwLock::new(Snapshot::default()));
+
+	tokio::spawn(poll(
+		client,
+		Duration::from_secs(arguments.interval_seconds),
+		Arc::clone(&snapshot),
+	));
+
+	serve(arguments.listen_address, snapshot).await
+}
+
+/// Polls the gateway on `interval` forever, storing each successful reading
+/// in `snapshot`. Failed polls are logged and skipped; the previous reading
+/// is kept until the next successful poll.
+async fn poll(client: Client, interval: Duration, snapshot: Arc<RwLock<Snapshot>>) {
+	let mut interval = tokio::time::interval(interval);
+
+	loop {
+		interval.tick().await;
+
+		let meters = report(client.meters_aggregates().await);
+		let system_status = report(client.system_status().await);
+		let state_of_energy = report(client.state_of_energy().await).map(|soe| soe.percentage);
+
+		let mut snapshot = snapshot.write().await;
+		if let Some(meters) = meters {
+			snapshot.meters = Some(meters);
+		}
+		if let Some(system_status) = system_status {
+			snapshot.system_status = Some(system_status);
+		}
+		if let Some(state_of_energy) = state_of_energy {
+			snapshot.state_of_energy = Some(state_of_energy);
+		}
+	}
+}
+
+fn report<T>(result: Result<T, Error>) -> Option<T> {
+	match result {
+		Ok(value) => Some(value),
+		Err(error) => {
+			eprintln!("poll failed: {error}");
+			None
+		}
+	}
+}
+
+async fn serve(listen_address: SocketAddr, snapshot: Arc<RwLock<Snapshot>>) -> anyhow::Result<()> {
+	let listener = TcpListener::bind(listen_address).await?;
+	println!("serving /metrics on http://{listen_address}");
+
+	loop {
+		let (stream, _) = listener.accept().await?;
+		let snapshot = Arc::clone(&snapshot);
+		tokio::spawn(async move {
+			if let Err(error) = handle_connection(stream, snapshot).await {
+				eprintln!("connection error: {error}");
+			}
+		});
+	}
+}
+
+async fn handle_connection(
+	mut stream: TcpStream,
+	snapshot: Arc<RwLock<Snapshot>>,
+) -> io::Result<()> {
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+	// The request is read and discarded without being inspected; every
+	// request gets the metrics, whatever its path.
+	let mut buffer = [0u8; 1024];
+	let _ = stream.read(&mut buffer).await?;
+
+	let body = render_metrics(&*snapshot.read().await);
+	let response = format!(
+		"HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+		body.len()
+	);
+
+	stream.write_all(response.as_bytes()).await?;
+	stream.write_all(body.as_bytes()).await?;
+	stream.flush().await?;
+
+	Ok(())
+}
+
+/// The `class` label to use for a given [`AggregateClass`].
+fn class_label(class: AggregateClass) -> &'static str {
+	match class {
+		AggregateClass::Load => "load",
+		AggregateClass::Grid => "grid",
+		AggregateClass::Battery => "battery",
+		AggregateClass::Solar => "solar",
+	}
+}
+
+/// Renders the current snapshot in the Prometheus text exposition format.
+fn render_metrics(snapshot: &Snapshot) -> String {
+	let mut out = String::new();
+
+	if let Some(meters) = &snapshot.meters {
+		for (class, device) in meters.sources().chain(meters.sinks()) {
+			let class = class_label(class);
+
+			let _ = writeln!(
+				out,
+				"powerwall_instant_power_watts{{class=\"{class}\"}} {}",
+				device.instant_power
+			);
+			let _ = writeln!(
+				out,
+				"powerwall_energy_imported_wh{{class=\"{class}\"}} {}",
+				device.energy_imported
+			);
+			let _ = writeln!(
+				out,
+				"powerwall_energy_exported_wh{{class=\"{class}\"}} {}",
+				device.energy_exported
+			);
+		}
+	}
+
+	if let Some(percentage) = snapshot.state_of_energy {
+		let _ = writeln!(out, "powerwall_state_of_energy_percent {percentage}");
+	}
+
+	if let Some(system_status) = &snapshot.system_status {
+		let _ = writeln!(
+			out,
+			"powerwall_nominal_energy_remaining_wh {}",
+			system_status.nominal_energy_remaining
+		);
+
+		for block in &system_status.battery_blocks {
+			let _ = writeln!(
+				out,
+				"powerwall_battery_block_nominal_energy_remaining_wh{{package_serial_number=\"{}\"}} {}",
+				block.package_serial_number, block.nominal_energy_remaining
+			);
+			let _ = writeln!(
+				out,
+				"powerwall_battery_block_p_out_watts{{package_serial_number=\"{}\"}} {}",
+				block.package_serial_number, block.p_out
+			);
+		}
+	}
+
+	out
+}
+
+fn load_certificates_from_pem(path: &Path) -> io::Result<Vec<Certificate>> {
+	let file = File::open(path)?;
+	let mut reader = BufReader::new(file);
+	let certs = rustls_pemfile::certs(&mut reader)?;
+
+	Ok(certs.into_iter().map(Certificate).collect())
+}
@@ -11,7 +11,7 @@ use std::{
 	path::{Path, PathBuf},
 	sync::{Arc, Mutex},
 };
-use tega_client::Client;
+use tega_client::{Client, Credentials};
 use tokio::sync::oneshot;
 
 #[derive(Parser)]
@@ -27,6 +27,14 @@ struct Arguments {
 	/// Path to a PEM file containing the certificate for the Tesla Backup
 	/// Gateway 2.
 	certificate: PathBuf,
+
+	/// Username to log in with.
+	#[clap(long, env = "TEG_USERNAME", default_value = "customer")]
+	username: String,
+
+	/// Password to log in with.
+	#[clap(long, env = "TEG_PASSWORD", default_value = "")]
+	password: String,
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -34,7 +42,14 @@ async fn main() -> anyhow::Result<()> {
 	let arguments = Arguments::parse();
 	let certs = load_certificates_from_pem(&arguments.certificate)?;
 
-	let client = Client::new((arguments.ip_address, 443).try_into()?, certs)?;
+	let client = Client::new(
+		(arguments.ip_address, 443).try_into()?,
+		certs,
+		Credentials {
+			username: arguments.username,
+			password: arguments.password,
+		},
+	)?;
 	let status = client.status().await?;
 	println!("{status:#?}");
 
@@ -0,0 +1,65 @@
+use reqwest::StatusCode;
+
+/// Errors that can occur while talking to the Tesla Backup Gateway.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error(transparent)]
+	Http(#[from] reqwest::Error),
+
+	/// The gateway responded with a status code other than `200 OK` that
+	/// isn't covered by a more specific variant.
+	#[error("unexpected status {status} from {endpoint}: {body}")]
+	UnexpectedStatus {
+		endpoint: &'static str,
+		status: StatusCode,
+		body: String,
+	},
+
+	/// The response body could not be deserialized.
+	#[error("failed to deserialize response body: {0}")]
+	Deserialize(String),
+
+	/// The gateway responded `401 Unauthorized`. Endpoints that require a
+	/// session return this only after an automatic re-login and retry.
+	#[error("not authenticated")]
+	Unauthorized,
+
+	/// The gateway responded `403 Forbidden`; the session is valid but
+	/// lacks permission for this endpoint.
+	#[error("not permitted to access this endpoint")]
+	NotPermitted,
+
+	/// `Client::set_operation` was called with a `reserve_percent` outside
+	/// of `0..=100`.
+	#[error("backup reserve percent must be between 0 and 100, got {0}")]
+	InvalidBackupReservePercent(u8),
+
+	/// The gateway responded `200 OK` to `/api/operation` but its body
+	/// didn't report success.
+	#[error("gateway rejected the operation change: {0}")]
+	OperationRejected(String),
+}
+
+impl Error {
+	/// Turns a response into an [`Error`] if its status isn't `200 OK`.
+	/// The body text is captured for diagnostics in
+	/// [`Error::UnexpectedStatus`]; for `401` and `403` it is discarded.
+	pub(crate) async fn from_response(
+		endpoint: &'static str,
+		response: reqwest::Response,
+	) -> Result<reqwest::Response, Error> {
+		match response.status() {
+			StatusCode::OK => Ok(response),
+			StatusCode::UNAUTHORIZED => Err(Error::Unauthorized),
+			StatusCode::FORBIDDEN => Err(Error::NotPermitted),
+			status => {
+				let body = response.text().await.unwrap_or_default();
+				Err(Error::UnexpectedStatus {
+					endpoint,
+					status,
+					body,
+				})
+			}
+		}
+	}
+}
@@ -1,23 +1,80 @@
+use futures::Stream;
 use reqwest::{
-	header::{HeaderMap, HeaderValue, ACCEPT},
+	header::{HeaderMap, HeaderValue, ACCEPT, COOKIE},
 	ClientBuilder, StatusCode, Url,
 };
 use rustls::{
 	client::{ServerCertVerified, ServerCertVerifier},
 	Certificate, RootCertStore,
 };
-use serde::Serialize;
-use std::{fmt, sync::Arc};
-use tega_types::{legal::Radio, login::LoginBasic, meters::MetersAggregates, status::Status};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	fmt,
+	sync::{Arc, RwLock},
+	time::Duration,
+};
+use tega_types::{
+	legal::Radio,
+	login::LoginBasic,
+	meters::MetersAggregates,
+	operation::{Operation, RealMode, SetOperation, SetOperationResponse},
+	status::Status,
+	system_status::{StateOfEnergy, SystemStatus},
+	vitals::Vitals,
+	OffsetDateTime,
+};
+
+mod error;
+
+pub use error::Error;
 
 // These need to match.
 const BASE_NAME: &str = "teg";
 const BASE_URL: &str = "https://teg";
 
-#[derive(Clone, Debug)]
+/// Credentials used to log in to the gateway and to transparently re-login
+/// if a session expires.
+#[derive(Clone)]
+pub struct Credentials {
+	pub username: String,
+	pub password: String,
+}
+
+impl fmt::Debug for Credentials {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Credentials")
+			.field("username", &self.username)
+			.field("password", &"<redacted>")
+			.finish()
+	}
+}
+
+#[derive(Clone)]
 pub struct Client {
 	base: Url,
 	inner_client: reqwest::Client,
+	credentials: Credentials,
+	/// The bearer token from the most recent successful login, if any.
+	session: Arc<RwLock<Option<String>>>,
+}
+
+impl fmt::Debug for Client {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Client")
+			.field("base", &self.base)
+			.field("inner_client", &self.inner_client)
+			.field("credentials", &self.credentials)
+			.field(
+				"session",
+				&self
+					.session
+					.read()
+					.unwrap()
+					.as_ref()
+					.map(|_| "<redacted>"),
+			)
+			.finish()
+	}
 }
 
 #[derive(Debug)]
@@ -57,7 +114,8 @@ impl Client {
 	pub fn new(
 		addr: std::net::SocketAddr,
 		certificates: Vec<Certificate>,
-	) -> Result<Self, reqwest::Error> {
+		credentials: Credentials,
+	) -> Result<Self, Error> {
 		let base = Url::parse(BASE_URL).expect("BASE_URL must be valid");
 
 		let mut config = rustls::ClientConfig::builder()
@@ -83,14 +141,25 @@ impl Client {
 			.default_headers(default_headers)
 			.build()?;
 
-		Ok(Self { base, inner_client })
+		Ok(Self {
+			base,
+			inner_client,
+			credentials,
+			session: Arc::new(RwLock::new(None)),
+		})
 	}
 
+	/// Logs in with the given credentials, storing the returned session
+	/// token so it is sent with subsequent requests to protected endpoints.
+	///
+	/// Most callers don't need to call this directly: [`Client::new`] takes
+	/// [`Credentials`] and authenticated methods log in automatically on
+	/// first use and transparently re-login if the session expires.
 	pub async fn login(
 		&self,
 		username: impl AsRef<str>,
 		password: impl AsRef<str>,
-	) -> Result<LoginBasic, reqwest::Error> {
+	) -> Result<LoginBasic, Error> {
 		#[derive(Serialize)]
 		struct RequestBody<'a> {
 			#[serde(borrow)]
@@ -111,46 +180,278 @@ impl Client {
 			.send()
 			.await?;
 
-		assert_eq!(response.status(), StatusCode::OK);
-		let body = response.json().await?;
+		let response = Error::from_response("/api/login/Basic", response).await?;
+		let body: LoginBasic = response
+			.json()
+			.await
+			.map_err(|error| Error::Deserialize(error.to_string()))?;
+
+		*self.session.write().unwrap() = Some(body.token.clone());
 
 		Ok(body)
 	}
 
-	/// Fetches the `/api/meters/aggregates` endpoint from the gateway.
-	pub async fn meters_aggregates(&self) -> Result<MetersAggregates, reqwest::Error> {
-		let url = self.base.join("/api/meters/aggregates").unwrap();
+	/// Re-runs the Basic login using the credentials supplied to
+	/// [`Client::new`], storing and returning the new session token.
+	async fn reauthenticate(&self) -> Result<String, Error> {
+		let credentials = self.credentials.clone();
+		let body = self.login(credentials.username, credentials.password).await?;
 
-		let response = self.inner_client.get(url).send().await?;
+		Ok(body.token)
+	}
 
-		assert_eq!(response.status(), StatusCode::OK);
-		let body = response.json().await?;
+	/// Returns the current session token, logging in first if there isn't
+	/// one yet.
+	async fn ensure_session(&self) -> Result<String, Error> {
+		if let Some(token) = self.session.read().unwrap().clone() {
+			return Ok(token);
+		}
 
-		Ok(body)
+		self.reauthenticate().await
+	}
+
+	/// Sends a request built by `build` against an endpoint that requires an
+	/// authenticated session, transparently re-logging in and retrying once
+	/// if the session has expired.
+	///
+	/// `build` is called with the inner HTTP client, the joined URL, and the
+	/// current session token, and is expected to return a `RequestBuilder`
+	/// ready to `.send()` — it may be called twice, so it must not consume
+	/// anything it can't produce again (e.g. a streaming body).
+	async fn send_authenticated(
+		&self,
+		endpoint: &'static str,
+		build: impl Fn(&reqwest::Client, Url, &str) -> reqwest::RequestBuilder,
+	) -> Result<reqwest::Response, Error> {
+		let url = self.base.join(endpoint).unwrap();
+		let mut token = self.ensure_session().await?;
+
+		let mut response = build(&self.inner_client, url.clone(), &token).send().await?;
+
+		if matches!(
+			response.status(),
+			StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+		) {
+			token = self.reauthenticate().await?;
+			response = build(&self.inner_client, url, &token).send().await?;
+		}
+
+		Error::from_response(endpoint, response).await
+	}
+
+	/// Fetches and deserializes a JSON endpoint that requires an
+	/// authenticated session, transparently re-logging in and retrying once
+	/// if the session has expired.
+	async fn get_authenticated<T: DeserializeOwned>(
+		&self,
+		endpoint: &'static str,
+	) -> Result<T, Error> {
+		let response = self
+			.send_authenticated(endpoint, |client, url, token| {
+				client
+					.get(url)
+					.header(COOKIE, format!("AuthCookie={token}"))
+					.bearer_auth(token)
+			})
+			.await?;
+
+		response
+			.json()
+			.await
+			.map_err(|error| Error::Deserialize(error.to_string()))
+	}
+
+	/// Posts a JSON body to an endpoint that requires an authenticated
+	/// session, transparently re-logging in and retrying once if the
+	/// session has expired.
+	async fn post_authenticated<B: Serialize, T: DeserializeOwned>(
+		&self,
+		endpoint: &'static str,
+		body: &B,
+	) -> Result<T, Error> {
+		let response = self
+			.send_authenticated(endpoint, |client, url, token| {
+				client
+					.post(url)
+					.header(COOKIE, format!("AuthCookie={token}"))
+					.bearer_auth(token)
+					.json(body)
+			})
+			.await?;
+
+		response
+			.json()
+			.await
+			.map_err(|error| Error::Deserialize(error.to_string()))
+	}
+
+	/// Fetches the `/api/operation` endpoint from the gateway, returning the
+	/// current operating mode and backup reserve.
+	///
+	/// This endpoint requires an authenticated session.
+	pub async fn get_operation(&self) -> Result<Operation, Error> {
+		self.get_authenticated("/api/operation").await
+	}
+
+	/// Sets the Powerwall's operating mode and backup reserve via
+	/// `/api/operation`.
+	///
+	/// This endpoint requires an authenticated session. Returns
+	/// [`Error::InvalidBackupReservePercent`] if `reserve_percent` is
+	/// greater than `100`, or [`Error::OperationRejected`] if the gateway
+	/// responds `200 OK` without reporting success in the body.
+	pub async fn set_operation(
+		&self,
+		mode: RealMode,
+		reserve_percent: u8,
+	) -> Result<(), Error> {
+		if reserve_percent > 100 {
+			return Err(Error::InvalidBackupReservePercent(reserve_percent));
+		}
+
+		let response: SetOperationResponse = self
+			.post_authenticated(
+				"/api/operation",
+				&SetOperation {
+					real_mode: mode,
+					backup_reserve_percent: reserve_percent,
+				},
+			)
+			.await?;
+
+		if response.result != "Updated" {
+			return Err(Error::OperationRejected(response.result));
+		}
+
+		Ok(())
+	}
+
+	/// Fetches the `/api/system_status` endpoint from the gateway.
+	///
+	/// This endpoint requires an authenticated session.
+	pub async fn system_status(&self) -> Result<SystemStatus, Error> {
+		self.get_authenticated("/api/system_status").await
+	}
+
+	/// Fetches the `/api/system_status/soe` endpoint from the gateway.
+	///
+	/// This endpoint requires an authenticated session.
+	pub async fn state_of_energy(&self) -> Result<StateOfEnergy, Error> {
+		self.get_authenticated("/api/system_status/soe").await
+	}
+
+	/// Fetches the `/api/meters/aggregates` endpoint from the gateway.
+	///
+	/// This endpoint requires an authenticated session.
+	pub async fn meters_aggregates(&self) -> Result<MetersAggregates, Error> {
+		self.get_authenticated("/api/meters/aggregates").await
 	}
 
 	/// Fetches the `/api/status` endpoint from the gateway.
 	///
 	/// This endpoint does not require authentication.
-	pub async fn status(&self) -> Result<Status, reqwest::Error> {
+	pub async fn status(&self) -> Result<Status, Error> {
 		let url = self.base.join("/api/status").unwrap();
 
 		let response = self.inner_client.get(url).send().await?;
-
-		assert_eq!(response.status(), StatusCode::OK);
-		let body = response.json().await?;
+		let response = Error::from_response("/api/status", response).await?;
+		let body = response
+			.json()
+			.await
+			.map_err(|error| Error::Deserialize(error.to_string()))?;
 
 		Ok(body)
 	}
 
-	pub async fn legal_radio(&self) -> Result<Vec<Radio>, reqwest::Error> {
+	/// Fetches the `/api/devices/vitals` endpoint from the gateway and decodes
+	/// its protobuf body into per-device telemetry.
+	pub async fn devices_vitals(&self) -> Result<Vitals, Error> {
+		let url = self.base.join("/api/devices/vitals").unwrap();
+
+		let response = self
+			.inner_client
+			.get(url)
+			.header(ACCEPT, HeaderValue::from_static("application/octet-stream"))
+			.send()
+			.await?;
+
+		let response = Error::from_response("/api/devices/vitals", response).await?;
+		let body = response.bytes().await?;
+
+		Vitals::decode(&body).map_err(|error| Error::Deserialize(error.to_string()))
+	}
+
+	pub async fn legal_radio(&self) -> Result<Vec<Radio>, Error> {
 		let url = self.base.join("/api/legal/radio").unwrap();
 
 		let response = self.inner_client.get(url).send().await?;
-
-		assert_eq!(response.status(), StatusCode::OK);
-		let body = response.json().await?;
+		let response = Error::from_response("/api/legal/radio", response).await?;
+		let body = response
+			.json()
+			.await
+			.map_err(|error| Error::Deserialize(error.to_string()))?;
 
 		Ok(body)
 	}
+
+	/// A combined, repeated reading of `meters_aggregates` and
+	/// `state_of_energy`, taken once per `interval`.
+	///
+	/// Consumers that need this for logging or charge control no longer
+	/// have to hand-roll their own polling loop. Missed ticks (e.g. because
+	/// a previous poll took longer than `interval`) coalesce into a single
+	/// tick rather than bursting to catch up. `jitter` adds a random delay
+	/// of up to that duration before each poll, so that many gateways
+	/// scraped by the same process don't all request at once.
+	pub fn sample_stream(
+		&self,
+		interval: Duration,
+		jitter: Duration,
+	) -> impl Stream<Item = Result<Sample, Error>> + '_ {
+		let mut ticker = tokio::time::interval(interval);
+		ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+		futures::stream::unfold((self, ticker), move |(client, mut ticker)| async move {
+			ticker.tick().await;
+
+			if !jitter.is_zero() {
+				tokio::time::sleep(jittered_delay(jitter)).await;
+			}
+
+			let sample = client.sample().await;
+			Some((sample, (client, ticker)))
+		})
+	}
+
+	async fn sample(&self) -> Result<Sample, Error> {
+		let meters = self.meters_aggregates().await?;
+		let soe = self.state_of_energy().await?;
+
+		Ok(Sample {
+			timestamp: OffsetDateTime::now_utc(),
+			meters,
+			soe,
+		})
+	}
+}
+
+/// A single reading yielded by [`Client::sample_stream`].
+#[derive(Debug)]
+pub struct Sample {
+	pub timestamp: OffsetDateTime,
+	pub meters: MetersAggregates,
+	pub soe: StateOfEnergy,
+}
+
+/// A pseudo-random delay in `0..jitter`, used to spread out scraping of
+/// multiple gateways that share a polling interval.
+fn jittered_delay(jitter: Duration) -> Duration {
+	const NANOS_PER_SEC: f64 = 1_000_000_000.0;
+
+	let nanos = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.unwrap_or_default()
+		.subsec_nanos();
+
+	jitter.mul_f64(nanos as f64 / NANOS_PER_SEC)
 }
@@ -4,9 +4,16 @@ pub use time::OffsetDateTime;
 pub mod legal;
 pub mod login;
 pub mod meters;
+pub mod operation;
 pub mod status;
 pub mod system_status;
+pub mod vitals;
 
 mod serde;
 
+#[allow(clippy::all, missing_docs)]
+mod protos {
+	include!(concat!(env!("OUT_DIR"), "/tesla-protos/mod.rs"));
+}
+
 type Float = f64;
@@ -152,6 +152,67 @@ impl MetersAggregates {
 	}
 }
 
+/// One edge of a proportional power-flow allocation, as returned by
+/// [`MetersAggregates::flows`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Flow {
+	pub from: AggregateClass,
+	pub to: AggregateClass,
+
+	/// Power in Watts flowing from `from` to `to`.
+	pub power: Float,
+}
+
+/// Flows below this threshold are omitted from [`MetersAggregates::flows`].
+const FLOW_EPSILON: Float = 1e-6;
+
+impl MetersAggregates {
+	/// Allocates each sink's demand across sources in proportion to each
+	/// source's share of the total power available, e.g. solar→home,
+	/// solar→battery, battery→home, grid→home.
+	///
+	/// This is a merit-order approximation, not a measurement: the gateway
+	/// doesn't report which electron came from where, so when total supply
+	/// and total demand disagree (conversion losses), the allocation is
+	/// normalized against whichever total is larger so that no source is
+	/// ever shown feeding more than its own `instant_power`.
+	pub fn flows(&self) -> Vec<Flow> {
+		let sources: Vec<(AggregateClass, Float)> = self
+			.sources()
+			.map(|(class, device)| (class, device.instant_power))
+			.collect();
+
+		let total_source: Float = sources.iter().map(|(_, power)| power).sum();
+		if sources.is_empty() || total_source <= 0.0 {
+			return Vec::new();
+		}
+
+		let sinks: Vec<(AggregateClass, Float)> = self
+			.sinks()
+			.map(|(class, device)| (class, device.instant_power.abs()))
+			.collect();
+
+		let total_demand: Float = sinks.iter().map(|(_, demand)| demand).sum();
+		let normalizer = total_source.max(total_demand);
+
+		let mut flows = Vec::new();
+		for (to, demand) in &sinks {
+			for (from, source_power) in &sources {
+				let power = demand * (source_power / normalizer);
+				if power > FLOW_EPSILON {
+					flows.push(Flow {
+						from: *from,
+						to: *to,
+						power,
+					});
+				}
+			}
+		}
+
+		flows
+	}
+}
+
 fn default_num_meters() -> u16 {
 	1
 }
@@ -179,4 +240,29 @@ mod tests {
 
 		dbg!(total_generation, total_usage);
 	}
+
+	#[test]
+	fn flows_sample() {
+		let sample = include_bytes!("../../samples/api-meters-aggregates.json");
+		let meters: MetersAggregates = serde_json::from_slice(sample).unwrap();
+
+		let flows = meters.flows();
+		assert!(!flows.is_empty());
+
+		for source in meters.sources().map(|(class, _)| class) {
+			let total: f64 = flows
+				.iter()
+				.filter(|flow| flow.from == source)
+				.map(|flow| flow.power)
+				.sum();
+			let available = meters
+				.sources()
+				.find(|(class, _)| *class == source)
+				.unwrap()
+				.1
+				.instant_power;
+
+			assert!(total <= available + 1e-6);
+		}
+	}
 }
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+/// The Powerwall's operating mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RealMode {
+	SelfConsumption,
+	Backup,
+	Autonomous,
+}
+
+/// Payload returned from `GET /api/operation`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Operation {
+	pub real_mode: RealMode,
+	pub backup_reserve_percent: u8,
+}
+
+/// Request body for `POST /api/operation`.
+#[derive(Debug, Serialize)]
+pub struct SetOperation {
+	pub real_mode: RealMode,
+	pub backup_reserve_percent: u8,
+}
+
+/// Payload returned from `POST /api/operation`.
+#[derive(Debug, Deserialize)]
+pub struct SetOperationResponse {
+	pub result: String,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Operation, RealMode};
+
+	#[test]
+	fn deserialize_operation() {
+		let sample = include_bytes!("../samples/api-operation.json");
+		let operation: Operation = serde_json::from_slice(sample).unwrap();
+
+		assert_eq!(operation.real_mode, RealMode::SelfConsumption);
+		assert_eq!(operation.backup_reserve_percent, 20);
+	}
+}
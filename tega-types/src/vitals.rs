@@ -0,0 +1,228 @@
+//! Typed wrappers over the `/api/devices/vitals` protobuf payload.
+//!
+//! The gateway encodes vitals as a flat [`protos::DeviceMap`] whose keys are
+//! compound identifiers of the form `{component}--{din}--{field}` (e.g.
+//! `PVAC--3012345-00-E--PVAC_Pout`). [`Vitals::decode`] splits those keys and
+//! groups the fields back up by component and DIN.
+
+use std::collections::BTreeMap;
+
+use crate::Float;
+use crate::protos::tesla::{DeviceMap, StringValue, string_value};
+use protobuf::Message;
+
+/// A single field value inside a device's [`Fields`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+	String(String),
+	Int(i64),
+	Float(Float),
+	Bool(bool),
+}
+
+impl Value {
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Value::String(s) => Some(s),
+			_ => None,
+		}
+	}
+
+	pub fn as_i64(&self) -> Option<i64> {
+		match self {
+			Value::Int(v) => Some(*v),
+			_ => None,
+		}
+	}
+
+	pub fn as_f64(&self) -> Option<Float> {
+		match self {
+			Value::Float(v) => Some(*v),
+			Value::Int(v) => Some(*v as Float),
+			_ => None,
+		}
+	}
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match self {
+			Value::Bool(v) => Some(*v),
+			_ => None,
+		}
+	}
+}
+
+impl From<StringValue> for Value {
+	fn from(value: StringValue) -> Self {
+		match value.value {
+			Some(string_value::Value::StringValue(v)) => Value::String(v),
+			Some(string_value::Value::IntValue(v)) => Value::Int(v),
+			Some(string_value::Value::FloatValue(v)) => Value::Float(v),
+			Some(string_value::Value::BoolValue(v)) => Value::Bool(v),
+			None => Value::String(String::new()),
+		}
+	}
+}
+
+/// The fields belonging to a single device, keyed by field name with the
+/// `{component}--{din}--` prefix already stripped.
+#[derive(Clone, Debug, Default)]
+pub struct Fields(BTreeMap<String, Value>);
+
+impl Fields {
+	pub fn get(&self, field: &str) -> Option<&Value> {
+		self.0.get(field)
+	}
+
+	pub fn float(&self, field: &str) -> Option<Float> {
+		self.get(field).and_then(Value::as_f64)
+	}
+
+	pub fn bool(&self, field: &str) -> Option<bool> {
+		self.get(field).and_then(Value::as_bool)
+	}
+
+	pub fn str(&self, field: &str) -> Option<&str> {
+		self.get(field).and_then(Value::as_str)
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+		self.0.iter().map(|(k, v)| (k.as_str(), v))
+	}
+}
+
+/// A single device's vitals, e.g. one string of a PVS or one pack of a
+/// battery block.
+#[derive(Clone, Debug)]
+pub struct Device {
+	/// The device identification number, shared with [`crate::status::Status::din`]
+	/// for the leader device, or the pack/string serial for subordinate hardware.
+	pub din: String,
+
+	/// The component kind, as it appears on the wire (`"PINV"`, `"PVAC"`,
+	/// `"PVS"`, `"TESLA"`, ...).
+	pub component: String,
+
+	pub fields: Fields,
+}
+
+/// Decoded payload from the `/api/devices/vitals` endpoint.
+#[derive(Clone, Debug, Default)]
+pub struct Vitals {
+	devices: Vec<Device>,
+}
+
+impl Vitals {
+	/// Decodes a raw protobuf-encoded `/api/devices/vitals` response body.
+	pub fn decode(bytes: &[u8]) -> protobuf::Result<Self> {
+		let map = DeviceMap::parse_from_bytes(bytes)?;
+
+		let mut grouped: BTreeMap<(String, String), Fields> = BTreeMap::new();
+		for (key, value) in map.devices {
+			let Some((component, din, field)) = split_key(&key) else {
+				continue;
+			};
+
+			grouped
+				.entry((component.to_owned(), din.to_owned()))
+				.or_default()
+				.0
+				.insert(field.to_owned(), Value::from(value));
+		}
+
+		let devices = grouped
+			.into_iter()
+			.map(|((component, din), fields)| Device { din, component, fields })
+			.collect();
+
+		Ok(Self { devices })
+	}
+
+	pub fn devices(&self) -> &[Device] {
+		&self.devices
+	}
+
+	/// Devices whose component matches `component` exactly (e.g. `"PVAC"`).
+	pub fn by_component<'a>(&'a self, component: &'a str) -> impl Iterator<Item = &'a Device> {
+		self.devices.iter().filter(move |device| device.component == component)
+	}
+
+	/// String inverters.
+	pub fn pinv(&self) -> impl Iterator<Item = &Device> {
+		self.by_component("PINV")
+	}
+
+	/// Per-string power conversion data.
+	pub fn pvac(&self) -> impl Iterator<Item = &Device> {
+		self.by_component("PVAC")
+	}
+
+	/// Per-string solar data.
+	pub fn pvs(&self) -> impl Iterator<Item = &Device> {
+		self.by_component("PVS")
+	}
+
+	/// Battery blocks (Powerwall packs).
+	pub fn battery_blocks(&self) -> impl Iterator<Item = &Device> {
+		self.by_component("TESLA")
+	}
+}
+
+/// Splits a `{component}--{din}--{field}` key into its three parts.
+///
+/// The DIN segment can itself contain `--` (e.g.
+/// `1152100-13-J--AB123456C7D8EF`), so this must be parsed from the outside
+/// in: peel the field off the right, then the component off the left of
+/// what remains, and whatever's left in the middle is the DIN.
+fn split_key(key: &str) -> Option<(&str, &str, &str)> {
+	let (rest, field) = key.rsplit_once("--")?;
+	let (component, din) = rest.split_once("--")?;
+
+	Some((component, din, field))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Vitals;
+	use crate::protos::tesla::{string_value, DeviceMap, StringValue};
+	use protobuf::Message;
+
+	fn float_value(value: f64) -> StringValue {
+		StringValue {
+			value: Some(string_value::Value::FloatValue(value)),
+			..Default::default()
+		}
+	}
+
+	fn string_value(value: &str) -> StringValue {
+		StringValue {
+			value: Some(string_value::Value::StringValue(value.to_owned())),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn decode_splits_dins_containing_double_dashes() {
+		// A real Tesla gateway DIN, which itself contains "--".
+		let din = "1152100-13-J--AB123456C7D8EF";
+
+		let mut map = DeviceMap::default();
+		map.devices
+			.insert(format!("PVAC--{din}--PVAC_Pout"), float_value(1234.5));
+		map.devices
+			.insert(format!("PVAC--{din}--PVAC_State"), string_value("PV_Active"));
+		map.devices
+			.insert(format!("PINV--{din}--PINV_Fstate"), string_value("IslandMode"));
+
+		let bytes = map.write_to_bytes().unwrap();
+		let vitals = Vitals::decode(&bytes).unwrap();
+
+		let pvac = vitals.pvac().next().expect("expected a PVAC device");
+		assert_eq!(pvac.din, din);
+		assert_eq!(pvac.fields.float("PVAC_Pout"), Some(1234.5));
+		assert_eq!(pvac.fields.str("PVAC_State"), Some("PV_Active"));
+
+		let pinv = vitals.pinv().next().expect("expected a PINV device");
+		assert_eq!(pinv.din, din);
+		assert_eq!(pinv.fields.str("PINV_Fstate"), Some("IslandMode"));
+	}
+}